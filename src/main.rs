@@ -1,16 +1,232 @@
 use dirs::picture_dir;
 use dotenv::dotenv;
 use reqwest::blocking::get;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::env;
 use std::fs::File;
 use std::io::copy;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
 
 // Struct to deserialize the API response
 #[derive(Debug, Deserialize)]
 struct ApiResponse {
-    hdurl: String,
+    media_type: String,
+    #[allow(dead_code)]
+    url: Option<String>,
+    hdurl: Option<String>,
+    thumbnail_url: Option<String>,
+}
+
+fn resolve_image_url(api_response: &ApiResponse) -> Option<&str> {
+    if api_response.media_type == "video" {
+        api_response.thumbnail_url.as_deref()
+    } else {
+        api_response.hdurl.as_deref()
+    }
+}
+
+#[derive(Debug)]
+struct ImageMeta {
+    url: Option<String>,
+    caption: Option<String>,
+}
+
+trait ImageSource {
+    fn fetch(&self) -> Result<ImageMeta, Box<dyn std::error::Error>>;
+}
+
+struct ApodSource {
+    api_key: String,
+}
+
+impl ImageSource for ApodSource {
+    fn fetch(&self) -> Result<ImageMeta, Box<dyn std::error::Error>> {
+        let api_url = format!(
+            "https://api.nasa.gov/planetary/apod?api_key={}&thumbs=True",
+            self.api_key
+        );
+        let api_response = fetch_image_data(&api_url)?;
+        let url = resolve_image_url(&api_response).map(|u| u.to_string());
+        Ok(ImageMeta { url, caption: None })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BingResponse {
+    images: Vec<BingImage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BingImage {
+    url: String,
+    copyright: Option<String>,
+}
+
+struct BingSource;
+
+fn bing_image_url(relative_url: &str) -> String {
+    let relative_url = relative_url.replace("_1920x1080.jpg", "_UHD.jpg");
+    format!("https://www.bing.com{relative_url}")
+}
+
+impl ImageSource for BingSource {
+    fn fetch(&self) -> Result<ImageMeta, Box<dyn std::error::Error>> {
+        let response = get("https://www.bing.com/HPImageArchive.aspx?format=js&idx=0&n=1")?;
+        let bing_response: BingResponse = response.json()?;
+        let image = bing_response
+            .images
+            .into_iter()
+            .next()
+            .ok_or("Bing returned no images")?;
+
+        Ok(ImageMeta {
+            url: Some(bing_image_url(&image.url)),
+            caption: image.copyright,
+        })
+    }
+}
+
+fn build_image_source() -> Box<dyn ImageSource> {
+    match env::var("XAPOD_SOURCE").unwrap_or_default().to_lowercase().as_str() {
+        "bing" => Box::new(BingSource),
+        _ => {
+            let api_key = env::var("APOD_KEY").expect("APOD_KEY must be set in the environment");
+            Box::new(ApodSource { api_key })
+        }
+    }
+}
+
+// Parses a humanized duration like "30s", "15m", "6h", or "1d".
+fn parse_interval(spec: &str) -> Result<Duration, Box<dyn std::error::Error>> {
+    let spec = spec.trim();
+    if spec.is_empty() {
+        return Err("interval must not be empty".into());
+    }
+
+    // Split on the last char, not the last byte, so a multi-byte unit errors
+    // out instead of panicking on a byte boundary.
+    let last_char = spec.chars().next_back().expect("spec is non-empty");
+    let split_at = spec.len() - last_char.len_utf8();
+    let (amount, unit) = spec.split_at(split_at);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("invalid interval amount: {}", spec))?;
+
+    if amount == 0 {
+        return Err(format!("interval must be greater than zero: {}", spec).into());
+    }
+
+    let seconds = match unit {
+        "s" => amount,
+        "m" => amount * 60,
+        "h" => amount * 3600,
+        "d" => amount * 86400,
+        other => return Err(format!("unrecognized interval unit: {}", other).into()),
+    };
+    Ok(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_interval_rejects_empty_input() {
+        assert!(parse_interval("").is_err());
+        assert!(parse_interval("   ").is_err());
+    }
+
+    #[test]
+    fn parse_interval_rejects_unrecognized_unit() {
+        assert!(parse_interval("30x").is_err());
+    }
+
+    #[test]
+    fn parse_interval_rejects_non_ascii_input_without_panicking() {
+        assert!(parse_interval("30µ").is_err());
+        assert!(parse_interval("µ").is_err());
+    }
+
+    #[test]
+    fn parse_interval_rejects_zero() {
+        assert!(parse_interval("0s").is_err());
+        assert!(parse_interval("0m").is_err());
+        assert!(parse_interval("0h").is_err());
+    }
+
+    #[test]
+    fn parse_interval_parses_known_units() {
+        assert_eq!(parse_interval("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_interval("6h").unwrap(), Duration::from_secs(6 * 3600));
+    }
+
+    fn api_response(media_type: &str, hdurl: Option<&str>, thumbnail_url: Option<&str>) -> ApiResponse {
+        ApiResponse {
+            media_type: media_type.to_string(),
+            url: None,
+            hdurl: hdurl.map(|s| s.to_string()),
+            thumbnail_url: thumbnail_url.map(|s| s.to_string()),
+        }
+    }
+
+    #[test]
+    fn resolve_image_url_prefers_hdurl_for_images() {
+        let response = api_response("image", Some("https://example.com/hd.jpg"), None);
+        assert_eq!(resolve_image_url(&response), Some("https://example.com/hd.jpg"));
+    }
+
+    #[test]
+    fn resolve_image_url_falls_back_to_thumbnail_for_videos() {
+        let response = api_response("video", None, Some("https://example.com/thumb.jpg"));
+        assert_eq!(resolve_image_url(&response), Some("https://example.com/thumb.jpg"));
+    }
+
+    #[test]
+    fn resolve_image_url_is_none_when_nothing_usable() {
+        let response = api_response("video", None, None);
+        assert_eq!(resolve_image_url(&response), None);
+    }
+
+    #[test]
+    fn bing_image_url_swaps_resolution_suffix_and_prefixes_host() {
+        assert_eq!(
+            bing_image_url("/th?id=OHR.Foo_1920x1080.jpg"),
+            "https://www.bing.com/th?id=OHR.Foo_UHD.jpg"
+        );
+    }
+
+    #[test]
+    fn bing_image_url_leaves_unrecognized_suffix_untouched() {
+        assert_eq!(
+            bing_image_url("/th?id=OHR.Foo_800x600.jpg"),
+            "https://www.bing.com/th?id=OHR.Foo_800x600.jpg"
+        );
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WallpaperState {
+    url: String,
+    path: PathBuf,
+}
+
+fn state_file_path(picture_dir: &Path) -> PathBuf {
+    picture_dir.join("apod_state.json")
+}
+
+// Requires `serde_json` as a dependency alongside `serde`.
+fn load_state(state_path: &Path) -> Option<WallpaperState> {
+    let data = std::fs::read_to_string(state_path).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+fn save_state(state_path: &Path, state: &WallpaperState) -> Result<(), Box<dyn std::error::Error>> {
+    let data = serde_json::to_string(state)?;
+    std::fs::write(state_path, data)?;
+    Ok(())
 }
 
 fn fetch_image_data(api_url: &str) -> Result<ApiResponse, Box<dyn std::error::Error>> {
@@ -27,6 +243,29 @@ fn download_image(url: &str, filename: &str) -> Result<PathBuf, Box<dyn std::err
     Ok(PathBuf::from(filename))
 }
 
+// Runs a user-supplied `WALLPAPER_COMMAND`, substituting `{path}` with the image path.
+fn run_custom_wallpaper_command(
+    command_template: &str,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let command = command_template.replace("{path}", &path.display().to_string());
+
+    let output = if cfg!(target_os = "windows") {
+        Command::new("cmd").args(&["/C", &command]).output()?
+    } else {
+        Command::new("sh").args(&["-c", &command]).output()?
+    };
+
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to run WALLPAPER_COMMAND: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+    Ok(())
+}
+
 #[cfg(target_os = "windows")]
 mod windows_background {
     use std::ffi::OsStr;
@@ -55,39 +294,106 @@ mod linux_background {
     use std::path::Path;
     use std::process::Command;
 
+    // `XDG_CURRENT_DESKTOP` can list several colon-separated names (e.g.
+    // `ubuntu:GNOME`); take the first one we recognize, falling back to
+    // `DESKTOP_SESSION` if none match.
+    fn resolve_desktop_env() -> String {
+        let xdg = env::var("XDG_CURRENT_DESKTOP").unwrap_or_default().to_lowercase();
+        if let Some(known) = xdg.split(':').find(|name| is_known(name)) {
+            return known.to_string();
+        }
+
+        env::var("DESKTOP_SESSION")
+            .unwrap_or_default()
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase()
+    }
+
+    fn is_known(name: &str) -> bool {
+        matches!(
+            name,
+            "gnome"
+                | "unity"
+                | "gnome-fallback"
+                | "kde"
+                | "cinnamon"
+                | "x-cinnamon"
+                | "mate"
+                | "x-mate"
+                | "deepin"
+                | "dde"
+                | "xfce"
+                | "lxde"
+        )
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn is_known_accepts_vendor_prefixed_names() {
+            for name in ["gnome", "x-cinnamon", "cinnamon", "x-mate", "mate", "dde", "deepin", "xfce", "lxde"] {
+                assert!(is_known(name), "{name} should be known");
+            }
+            assert!(!is_known("bspwm"));
+            assert!(!is_known(""));
+        }
+
+        #[test]
+        fn resolve_desktop_env_parses_colon_separated_xdg_value() {
+            env::set_var("XDG_CURRENT_DESKTOP", "ubuntu:GNOME");
+            env::remove_var("DESKTOP_SESSION");
+            assert_eq!(resolve_desktop_env(), "gnome");
+            env::remove_var("XDG_CURRENT_DESKTOP");
+        }
+
+        #[test]
+        fn resolve_desktop_env_falls_back_to_desktop_session() {
+            env::remove_var("XDG_CURRENT_DESKTOP");
+            env::set_var("DESKTOP_SESSION", "/usr/share/xsessions/cinnamon");
+            assert_eq!(resolve_desktop_env(), "cinnamon");
+            env::remove_var("DESKTOP_SESSION");
+        }
+    }
+
+    fn gsettings_set(schema: &str, key: &str, value: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let output = Command::new("gsettings")
+            .args(&["set", schema, key, value])
+            .output()?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "Failed to set wallpaper via {}: {}",
+                schema,
+                String::from_utf8_lossy(&output.stderr)
+            )
+            .into());
+        }
+        Ok(())
+    }
+
     pub fn set_wallpaper(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
-        let desktop_env = env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
-        match desktop_env.as_str() {
-            // Handle GNOME desktops
-            env if env.contains("GNOME") => {
-                let output = Command::new("gsettings")
-                    .args(&[
-                        "set",
-                        "org.gnome.desktop.background",
-                        "picture-uri",
-                        &format!("file://{}", path.display()),
-                    ])
-                    .output()?;
+        let desktop_env = resolve_desktop_env();
+        let uri = format!("file://{}", path.display());
 
-                if !output.status.success() {
-                    return Err(format!(
-                        "Failed to set GNOME wallpaper: {}",
-                        String::from_utf8_lossy(&output.stderr)
-                    )
-                    .into());
-                }
+        match desktop_env.as_str() {
+            // GNOME and GNOME-compliant desktops (Unity, gnome-fallback)
+            "gnome" | "unity" | "gnome-fallback" => {
+                gsettings_set("org.gnome.desktop.background", "picture-uri", &uri)?;
             }
-            // Handle KDE desktops
-            env if env.contains("KDE") => {
+            "kde" => {
                 let script = format!(
                     r#"
                 var allDesktops = desktops();
                     d = allDesktops[0];
                     d.wallpaperPlugin = "org.kde.image";
                     d.currentConfigGroup = Array("Wallpaper", "org.kde.image", "General");
-                    d.writeConfig("Image", "file://{}")
+                    d.writeConfig("Image", "{}")
                 "#,
-                    path.display()
+                    uri
                 );
 
                 let output = Command::new("qdbus")
@@ -107,6 +413,58 @@ mod linux_background {
                     .into());
                 }
             }
+            // Linux Mint's Cinnamon reports "X-Cinnamon"
+            "cinnamon" | "x-cinnamon" => {
+                gsettings_set("org.cinnamon.desktop.background", "picture-uri", &uri)?;
+            }
+            // Some distros report MATE as "X-MATE"
+            "mate" | "x-mate" => {
+                gsettings_set(
+                    "org.mate.desktop.background",
+                    "picture-filename",
+                    path.to_str().ok_or("wallpaper path is not valid UTF-8")?,
+                )?;
+            }
+            // Deepin Desktop Environment reports itself as "DDE" on some releases
+            "deepin" | "dde" => {
+                gsettings_set("com.deepin.wrap.gnome.desktop.background", "picture-uri", &uri)?;
+            }
+            "xfce" => {
+                let output = Command::new("xfconf-query")
+                    .args(&[
+                        "-c",
+                        "xfce4-desktop",
+                        "-p",
+                        "/backdrop/screen0/monitor0/workspace0/last-image",
+                        "-s",
+                        path.to_str().ok_or("wallpaper path is not valid UTF-8")?,
+                    ])
+                    .output()?;
+
+                if !output.status.success() {
+                    return Err(format!(
+                        "Failed to set XFCE wallpaper: {}",
+                        String::from_utf8_lossy(&output.stderr)
+                    )
+                    .into());
+                }
+            }
+            "lxde" => {
+                let output = Command::new("pcmanfm")
+                    .args(&[
+                        "--set-wallpaper",
+                        path.to_str().ok_or("wallpaper path is not valid UTF-8")?,
+                    ])
+                    .output()?;
+
+                if !output.status.success() {
+                    return Err(format!(
+                        "Failed to set LXDE wallpaper: {}",
+                        String::from_utf8_lossy(&output.stderr)
+                    )
+                    .into());
+                }
+            }
             _ => {
                 return Err("Unsupported desktop environment".into());
             }
@@ -115,38 +473,97 @@ mod linux_background {
     }
 }
 
-fn main() {
-    dotenv().ok();
+#[cfg(target_os = "macos")]
+mod macos_background {
+    use std::path::Path;
+    use std::process::Command;
+
+    pub fn set_wallpaper(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let script = format!(
+            r#"tell application "System Events" to set picture of every desktop to "{}""#,
+            path.display()
+        );
+
+        let output = Command::new("osascript").args(&["-e", &script]).output()?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "Failed to set macOS wallpaper: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
 
-    // Get API URL from environment variable
-    let api_key = env::var("APOD_KEY").expect("APOD_KEY must be set in the environment");
-    let api_url = format!("https://api.nasa.gov/planetary/apod?api_key={api_key}");
+fn refresh_wallpaper(source: &dyn ImageSource) {
+    match source.fetch() {
+        Ok(image_meta) => {
+            println!("Fetched image data: {:?}", image_meta);
+            if let Some(caption) = &image_meta.caption {
+                println!("Caption: {}", caption);
+            }
 
-    match fetch_image_data(&api_url) {
-        Ok(api_response) => {
-            println!("Fetched image data: {:?}", api_response);
+            let image_url = match image_meta.url {
+                Some(url) => url,
+                None => {
+                    println!("Today's image has no usable URL; leaving wallpaper untouched.");
+                    return;
+                }
+            };
 
             let filename = "apod.jpg";
-            let download_path = picture_dir()
-                .expect("Could not find picture directory")
-                .join(filename);
+            let picture_dir = picture_dir().expect("Could not find picture directory");
+            let download_path = picture_dir.join(filename);
+            let state_path = state_file_path(&picture_dir);
+
+            let already_applied = load_state(&state_path)
+                .map(|state| state.url == image_url && state.path.exists())
+                .unwrap_or(false);
+
+            if already_applied {
+                println!("Already up to date");
+                return;
+            }
 
-            match download_image(&api_response.hdurl, download_path.to_str().unwrap()) {
+            match download_image(&image_url, download_path.to_str().unwrap()) {
                 Ok(path) => {
                     println!("Image downloaded to {:?}", path);
 
-                    #[cfg(target_os = "windows")]
-                    {
-                        if let Err(e) = windows_background::set_wallpaper(&path) {
-                            eprintln!("Failed to set wallpaper on Windows: {}", e);
+                    if let Ok(command_template) = env::var("WALLPAPER_COMMAND") {
+                        if let Err(e) = run_custom_wallpaper_command(&command_template, &path) {
+                            eprintln!("Failed to set wallpaper via WALLPAPER_COMMAND: {}", e);
+                        }
+                    } else {
+                        #[cfg(target_os = "windows")]
+                        {
+                            if let Err(e) = windows_background::set_wallpaper(&path) {
+                                eprintln!("Failed to set wallpaper on Windows: {}", e);
+                            }
                         }
-                    }
 
-                    #[cfg(target_os = "linux")]
-                    {
-                        if let Err(e) = linux_background::set_wallpaper(&path) {
-                            eprintln!("Failed to set wallpaper on Linux: {}", e);
+                        #[cfg(target_os = "linux")]
+                        {
+                            if let Err(e) = linux_background::set_wallpaper(&path) {
+                                eprintln!("Failed to set wallpaper on Linux: {}", e);
+                            }
                         }
+
+                        #[cfg(target_os = "macos")]
+                        {
+                            if let Err(e) = macos_background::set_wallpaper(&path) {
+                                eprintln!("Failed to set wallpaper on macOS: {}", e);
+                            }
+                        }
+                    }
+
+                    let state = WallpaperState {
+                        url: image_url.clone(),
+                        path: path.clone(),
+                    };
+                    if let Err(e) = save_state(&state_path, &state) {
+                        eprintln!("Failed to persist wallpaper state: {}", e);
                     }
                 }
                 Err(e) => {
@@ -159,3 +576,21 @@ fn main() {
         }
     }
 }
+
+fn main() {
+    dotenv().ok();
+
+    let source = build_image_source();
+
+    match env::var("XAPOD_INTERVAL") {
+        Ok(interval_spec) => {
+            let interval =
+                parse_interval(&interval_spec).expect("Invalid XAPOD_INTERVAL");
+            loop {
+                refresh_wallpaper(source.as_ref());
+                thread::sleep(interval);
+            }
+        }
+        Err(_) => refresh_wallpaper(source.as_ref()),
+    }
+}